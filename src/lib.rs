@@ -14,15 +14,33 @@ extern crate bincode;
 extern crate zerocopy;
 
 use std::{mem, ptr, slice};
-use std::os::unix::io::{RawFd, FromRawFd, IntoRawFd, AsRawFd};
+use std::path::Path;
+use std::os::unix::io::{RawFd, FromRawFd, IntoRawFd, AsRawFd, OwnedFd};
+use std::sync::Once;
 use nix::{unistd, cmsg_space};
 use nix::fcntl::{self, FdFlag, FcntlArg};
+use nix::sys::signal::{signal, SigHandler, Signal};
 use nix::sys::uio::IoVec;
 use nix::sys::socket::{
     recvmsg, sendmsg, ControlMessageOwned, ControlMessage, MsgFlags,
-    socketpair, AddressFamily, SockFlag, SockType,
+    socketpair, socket, bind, listen, accept4, connect, CMSG_SPACE,
+    AddressFamily, SockAddr, SockFlag, SockType,
 };
 
+static IGNORE_SIGPIPE: Once = Once::new();
+
+/// Ignores `SIGPIPE` process-wide (once), so that writing to a socket whose peer has
+/// closed surfaces as `EPIPE`/`ErrorKind::PeerClosed` instead of killing the process.
+///
+/// `MsgFlags::MSG_NOSIGNAL` would do this per-call, but it isn't available in the nix
+/// version this crate targets, so `send_iovec` falls back to this coarser, process-wide
+/// approach instead.
+fn ignore_sigpipe() {
+    IGNORE_SIGPIPE.call_once(|| {
+        unsafe { let _ = signal(Signal::SIGPIPE, SigHandler::SigIgn); }
+    });
+}
+
 pub mod errors {
     error_chain!{
         foreign_links {
@@ -33,8 +51,25 @@ pub mod errors {
         }
 
         errors {
-            WrongRecvLength {
+            BadRecvSize(expected: usize, actual: usize) {
                 description("length of received message doesn't match the struct size or received length")
+                display("expected to receive {} bytes, but received {}", expected, actual)
+            }
+
+            RecvZero {
+                description("peer performed an orderly shutdown (received zero bytes)")
+            }
+
+            ControlMessageTruncated {
+                description("control message was truncated (MSG_CTRUNC); some passed descriptors were dropped by the kernel")
+            }
+
+            MessageTruncated {
+                description("received message was truncated (MSG_TRUNC); the receive buffer was too small")
+            }
+
+            PeerClosed {
+                description("the peer closed the connection (EPIPE)")
             }
         }
     }
@@ -78,11 +113,34 @@ impl Socket {
         }).map_err(|e| e.into())
     }
 
+    /// Creates a socket pair (AF_UNIX/SOCK_STREAM).
+    ///
+    /// Unlike `SOCK_SEQPACKET`, a stream socket doesn't preserve message boundaries,
+    /// so a single logical message may arrive split across several `recvmsg` calls;
+    /// use `recv_framed` to read length-prefixed messages sent with `send_slice_with_len`.
+    ///
+    /// Both sockets are close-on-exec by default.
+    pub fn new_socketpair_stream() -> Result<(Socket, Socket)> {
+        socketpair(AddressFamily::Unix, SockType::Stream, None, SockFlag::SOCK_CLOEXEC).map(|(a, b)| {
+            unsafe { (Self::from_raw_fd(a), Self::from_raw_fd(b)) }
+        }).map_err(|e| e.into())
+    }
+
     /// Disables close-on-exec on the socket (to preserve it across process forks).
     pub fn no_cloexec(&mut self) -> Result<()> {
         fcntl::fcntl(self.fd, FcntlArg::F_SETFD(FdFlag::empty())).map(|_| ()).map_err(|e| e.into())
     }
 
+    /// Connects to a `Listener` bound to the given path (AF_UNIX/SOCK_SEQPACKET).
+    ///
+    /// The resulting socket is close-on-exec by default.
+    pub fn connect<P: AsRef<Path>>(path: P) -> Result<Socket> {
+        let fd = socket(AddressFamily::Unix, SockType::SeqPacket, SockFlag::SOCK_CLOEXEC, None)?;
+        let addr = SockAddr::new_unix(path.as_ref())?;
+        connect(fd, &addr)?;
+        Ok(unsafe { Self::from_raw_fd(fd) })
+    }
+
     /// Reads bytes from the socket into the given scatter/gather array.
     ///
     /// If file descriptors were passed, returns them too.
@@ -94,6 +152,12 @@ impl Socket {
         let mut rfds = None;
         let mut cmsgspace = cmsg_space!(F);
         let msg = recvmsg(self.fd, iov, Some(&mut cmsgspace), MsgFlags::MSG_CMSG_CLOEXEC)?;
+        if msg.flags.contains(MsgFlags::MSG_CTRUNC) {
+            bail!(ErrorKind::ControlMessageTruncated);
+        }
+        if msg.flags.contains(MsgFlags::MSG_TRUNC) {
+            bail!(ErrorKind::MessageTruncated);
+        }
         for cmsg in msg.cmsgs() {
             if let ControlMessageOwned::ScmRights(fds) = cmsg {
                 if fds.len() >= 1 {
@@ -106,6 +170,44 @@ impl Socket {
         Ok((msg.bytes, rfds))
     }
 
+    /// Reads bytes from the socket into the given scatter/gather array, accepting an
+    /// unknown number of file descriptors (up to `max_fds`) instead of a fixed count
+    /// baked into the type of `F`.
+    ///
+    /// Returns however many descriptors actually arrived, collected across all control
+    /// messages in the packet, rather than requiring the caller to know the count ahead
+    /// of time.
+    ///
+    /// Received file descriptors are set close-on-exec.
+    pub fn recv_into_iovec_dyn(&mut self, iov: &[IoVec<&mut [u8]>], max_fds: usize) -> Result<(usize, Vec<RawFd>)> {
+        let mut rfds = Vec::new();
+        let cmsg_len = unsafe { CMSG_SPACE((mem::size_of::<RawFd>() * max_fds) as u32) } as usize;
+        let mut cmsgspace = vec![0u8; cmsg_len];
+        let msg = recvmsg(self.fd, iov, Some(&mut cmsgspace), MsgFlags::MSG_CMSG_CLOEXEC)?;
+        if msg.flags.contains(MsgFlags::MSG_CTRUNC) {
+            bail!(ErrorKind::ControlMessageTruncated);
+        }
+        if msg.flags.contains(MsgFlags::MSG_TRUNC) {
+            bail!(ErrorKind::MessageTruncated);
+        }
+        for cmsg in msg.cmsgs() {
+            if let ControlMessageOwned::ScmRights(fds) = cmsg {
+                rfds.extend(fds);
+            }
+        }
+        Ok((msg.bytes, rfds))
+    }
+
+    /// Like `recv_into_iovec_dyn`, but returns the descriptors as owned `OwnedFd`s instead
+    /// of bare `RawFd`s, so dropping the returned value closes them automatically instead
+    /// of leaving that to the caller.
+    ///
+    /// Received file descriptors are set close-on-exec.
+    pub fn recv_into_iovec_owned(&mut self, iov: &[IoVec<&mut [u8]>], max_fds: usize) -> Result<(usize, Vec<OwnedFd>)> {
+        let (bytes, rfds) = self.recv_into_iovec_dyn(iov, max_fds)?;
+        Ok((bytes, rfds.into_iter().map(|fd| unsafe { OwnedFd::from_raw_fd(fd) }).collect()))
+    }
+
     /// Reads bytes from the socket into the given buffer.
     ///
     /// If file descriptors were passed, returns them too.
@@ -156,6 +258,73 @@ impl Socket {
         Ok((bytes, buf, len, rfds))
     }
 
+    /// Reads a length-prefixed frame sent with `send_slice_with_len`, looping `recvmsg`
+    /// until the full frame has arrived. Use this on `SOCK_STREAM` sockets, where a single
+    /// logical message (including the 8-byte length prefix itself) may be split across
+    /// several `recvmsg` calls.
+    ///
+    /// `max_len` bounds the payload length read off the wire, the same way `buf_size`
+    /// bounds the allocations in `recv_into_buf`/`recv_cbor`/`recv_json`/`recv_bincode`,
+    /// so a peer can't make this allocate an unbounded buffer by sending a bogus length.
+    ///
+    /// File descriptors are only expected on the first packet of a frame, so to receive
+    /// them you need to instantiate the type parameter `F` as `[RawFd; n]`, where `n` is
+    /// the number of descriptors you want to receive; continuation reads don't look for
+    /// any control messages at all.
+    ///
+    /// Received file descriptors are set close-on-exec.
+    pub fn recv_framed<F: Default + AsMut<[RawFd]>>(&mut self, max_len: usize) -> Result<(Vec<u8>, Option<F>)> {
+        let mut len_buf = [0u8; mem::size_of::<u64>()];
+        let mut rfds = None;
+        let mut len_received;
+        {
+            let iov = [IoVec::from_mut_slice(&mut len_buf[..])];
+            let mut cmsgspace = cmsg_space!(F);
+            let msg = recvmsg(self.fd, &iov, Some(&mut cmsgspace), MsgFlags::MSG_CMSG_CLOEXEC)?;
+            if msg.flags.contains(MsgFlags::MSG_CTRUNC) {
+                bail!(ErrorKind::ControlMessageTruncated);
+            }
+            if msg.bytes == 0 {
+                bail!(ErrorKind::RecvZero);
+            }
+            for cmsg in msg.cmsgs() {
+                if let ControlMessageOwned::ScmRights(fds) = cmsg {
+                    if fds.len() >= 1 {
+                        let mut fd_arr: F = Default::default();
+                        <F as AsMut<[RawFd]>>::as_mut(&mut fd_arr).clone_from_slice(&fds);
+                        rfds = Some(fd_arr);
+                    }
+                }
+            }
+            len_received = msg.bytes;
+        }
+        // The length prefix itself may arrive split across several `recvmsg` calls on a
+        // stream socket; only the very first call above looks for control messages.
+        while len_received < len_buf.len() {
+            let iov = [IoVec::from_mut_slice(&mut len_buf[len_received..])];
+            let msg = recvmsg(self.fd, &iov, None, MsgFlags::empty())?;
+            if msg.bytes == 0 {
+                bail!(ErrorKind::RecvZero);
+            }
+            len_received += msg.bytes;
+        }
+        let len = u64::from_ne_bytes(len_buf) as usize;
+        if len > max_len {
+            bail!(ErrorKind::BadRecvSize(max_len, len));
+        }
+        let mut buf = vec![0u8; len];
+        let mut received = 0;
+        while received < len {
+            let iov = [IoVec::from_mut_slice(&mut buf[received..])];
+            let msg = recvmsg(self.fd, &iov, None, MsgFlags::empty())?;
+            if msg.bytes == 0 {
+                bail!(ErrorKind::RecvZero);
+            }
+            received += msg.bytes;
+        }
+        Ok((buf, rfds))
+    }
+
 
     /// See `recv_struct` for docs
     ///
@@ -166,14 +335,18 @@ impl Socket {
     /// such a type is UB.
     pub unsafe fn recv_struct_raw<T, F: Default + AsMut<[RawFd]>>(&mut self) -> Result<(T, Option<F>)> {
         let (bytes, buf, rfds) = self.recv_into_buf(mem::size_of::<T>())?;
+        if bytes == 0 {
+            bail!(ErrorKind::RecvZero);
+        }
         if bytes != mem::size_of::<T>() {
-            bail!(ErrorKind::WrongRecvLength);
+            bail!(ErrorKind::BadRecvSize(mem::size_of::<T>(), bytes));
         }
         Ok((ptr::read(buf.as_slice().as_ptr() as *const _), rfds))
     }
     
     /// Reads bytes from the socket and interprets them as a given data type.
-    /// If the size does not match, returns `WrongRecvLength`..
+    /// If fewer bytes than the size of `T` arrive, returns `BadRecvSize`; on an orderly peer
+    /// shutdown, returns `RecvZero`; if the message is larger than `T`, returns `MessageTruncated`.
     ///
     /// If file descriptors were passed, returns them too.
     /// To receive file descriptors, you need to instantiate the type parameter `F`
@@ -188,7 +361,8 @@ impl Socket {
     }
 
     /// Reads bytes from the socket and deserializes them as a given data type using CBOR.
-    /// If the size does not match, returns `WrongRecvLength`.
+    /// If the size does not match, returns `BadRecvSize`; on an orderly peer shutdown, returns `RecvZero`;
+    /// if the message doesn't fit in the receive buffer, returns `MessageTruncated`.
     ///
     /// You have to provide a size for the receive buffer.
     /// It should be large enough for the data you want to receive plus 64 bits for the length.
@@ -201,14 +375,18 @@ impl Socket {
     #[cfg(feature = "ser_cbor")]
     pub fn recv_cbor<T: serde::de::DeserializeOwned, F: Default + AsMut<[RawFd]>>(&mut self, buf_size: usize) -> Result<(T, Option<F>)> {
         let (bytes, buf, len, rfds) = self.recv_into_buf_with_len(buf_size)?;
+        if bytes == 0 {
+            bail!(ErrorKind::RecvZero);
+        }
         if bytes != len as usize + mem::size_of::<u64>() {
-            bail!(ErrorKind::WrongRecvLength);
+            bail!(ErrorKind::BadRecvSize(len as usize + mem::size_of::<u64>(), bytes));
         }
         Ok((serde_cbor::from_slice(&buf[..])?, rfds))
     }
 
     /// Reads bytes from the socket and deserializes them as a given data type using JSON.
-    /// If the size does not match, returns `WrongRecvLength`.
+    /// If the size does not match, returns `BadRecvSize`; on an orderly peer shutdown, returns `RecvZero`;
+    /// if the message doesn't fit in the receive buffer, returns `MessageTruncated`.
     ///
     /// You have to provide a size for the receive buffer.
     /// It should be large enough for the data you want to receive plus 64 bits for the length.
@@ -221,14 +399,18 @@ impl Socket {
     #[cfg(feature = "ser_json")]
     pub fn recv_json<T: serde::de::DeserializeOwned, F: Default + AsMut<[RawFd]>>(&mut self, buf_size: usize) -> Result<(T, Option<F>)> {
         let (bytes, buf, len, rfds) = self.recv_into_buf_with_len(buf_size)?;
+        if bytes == 0 {
+            bail!(ErrorKind::RecvZero);
+        }
         if bytes != len as usize + mem::size_of::<u64>() {
-            bail!(ErrorKind::WrongRecvLength);
+            bail!(ErrorKind::BadRecvSize(len as usize + mem::size_of::<u64>(), bytes));
         }
         Ok((serde_json::from_slice(&buf[..])?, rfds))
     }
 
     /// Reads bytes from the socket and deserializes them as a given data type using Bincode.
-    /// If the size does not match, returns `WrongRecvLength`.
+    /// If the size does not match, returns `BadRecvSize`; on an orderly peer shutdown, returns `RecvZero`;
+    /// if the message doesn't fit in the receive buffer, returns `MessageTruncated`.
     ///
     /// You have to provide a size for the receive buffer.
     /// It should be large enough for the data you want to receive plus 64 bits for the length.
@@ -241,8 +423,11 @@ impl Socket {
     #[cfg(feature = "ser_bincode")]
     pub fn recv_bincode<T: serde::de::DeserializeOwned, F: Default + AsMut<[RawFd]>>(&mut self, buf_size: usize) -> Result<(T, Option<F>)> {
         let (bytes, buf, len, rfds) = self.recv_into_buf_with_len(buf_size)?;
+        if bytes == 0 {
+            bail!(ErrorKind::RecvZero);
+        }
         if bytes != len as usize + mem::size_of::<u64>() {
-            bail!(ErrorKind::WrongRecvLength);
+            bail!(ErrorKind::BadRecvSize(len as usize + mem::size_of::<u64>(), bytes));
         }
         Ok((bincode::deserialize(&buf[..])?, rfds))
     }
@@ -250,12 +435,24 @@ impl Socket {
     /// Sends bytes from scatter-gather vectors over the socket.
     ///
     /// Optionally passes file descriptors with the message.
+    ///
+    /// Ignores `SIGPIPE` process-wide the first time this is called, so writing to a
+    /// socket whose peer has closed returns `ErrorKind::PeerClosed` instead of raising
+    /// `SIGPIPE` and killing the process.
     pub fn send_iovec(&mut self, iov: &[IoVec<&[u8]>], fds: Option<&[RawFd]>) -> Result<usize> {
-        if let Some(rfds) = fds {
-            sendmsg(self.fd, iov, &[ControlMessage::ScmRights(rfds)], MsgFlags::empty(), None).map_err(|e| e.into())
+        ignore_sigpipe();
+        let result = if let Some(rfds) = fds {
+            sendmsg(self.fd, iov, &[ControlMessage::ScmRights(rfds)], MsgFlags::empty(), None)
         } else {
-            sendmsg(self.fd, iov, &[], MsgFlags::empty(), None).map_err(|e| e.into())
-        }
+            sendmsg(self.fd, iov, &[], MsgFlags::empty(), None)
+        };
+        result.map_err(|e| {
+            if e.as_errno() == Some(nix::errno::Errno::EPIPE) {
+                ErrorKind::PeerClosed.into()
+            } else {
+                e.into()
+            }
+        })
     }
 
     /// Sends bytes from a slice over the socket.
@@ -266,6 +463,28 @@ impl Socket {
         self.send_iovec(&iov[..], fds)
     }
 
+    /// Sends all bytes from a slice over the socket, looping to handle partial writes
+    /// (which `sendmsg` may produce on `SOCK_STREAM` sockets).
+    ///
+    /// Descriptors are only attached to the first `sendmsg` call so they aren't
+    /// duplicated across retries. An empty `data` with `fds` set still issues one
+    /// `sendmsg` call, so the descriptors aren't silently dropped.
+    pub fn send_all(&mut self, data: &[u8], fds: Option<&[RawFd]>) -> Result<usize> {
+        if data.is_empty() {
+            if fds.is_some() {
+                self.send_iovec(&[IoVec::from_slice(data)], fds)?;
+            }
+            return Ok(0);
+        }
+        let mut sent = 0;
+        while sent < data.len() {
+            let iov = [IoVec::from_slice(&data[sent..])];
+            let fds_for_this_call = if sent == 0 { fds } else { None };
+            sent += self.send_iovec(&iov[..], fds_for_this_call)?;
+        }
+        Ok(sent)
+    }
+
     /// Sends bytes from a slice over the socket, prefixing with the length
     /// (as a 64-bit unsigned integer).
     ///
@@ -334,14 +553,197 @@ impl Drop for Socket {
     }
 }
 
+/// A listening AF_UNIX/SOCK_SEQPACKET socket bound to a filesystem path.
+///
+/// Use this to accept connections from independently-launched processes;
+/// for a pair of sockets within the same process tree, use `Socket::new_socketpair`.
+pub struct Listener {
+    fd: RawFd,
+}
+
+impl FromRawFd for Listener {
+    unsafe fn from_raw_fd(fd: RawFd) -> Listener {
+        Listener {
+            fd,
+        }
+    }
+}
+
+impl IntoRawFd for Listener {
+    fn into_raw_fd(self) -> RawFd {
+        let fd = self.fd;
+        std::mem::forget(self);
+        fd
+    }
+}
+
+impl AsRawFd for Listener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Listener {
+    /// Binds a listener to the given path.
+    ///
+    /// If `unlink` is true, removes a stale socket file at `path` (if any) before binding.
+    ///
+    /// The listening socket is close-on-exec by default.
+    pub fn new<P: AsRef<Path>>(path: P, unlink: bool) -> Result<Listener> {
+        let path = path.as_ref();
+        if unlink {
+            let _ = std::fs::remove_file(path);
+        }
+        let fd = socket(AddressFamily::Unix, SockType::SeqPacket, SockFlag::SOCK_CLOEXEC, None)?;
+        let addr = SockAddr::new_unix(path)?;
+        bind(fd, &addr)?;
+        listen(fd, 128)?;
+        Ok(Listener {
+            fd,
+        })
+    }
+
+    /// Accepts a single incoming connection, returning a connected `Socket`.
+    ///
+    /// Call this in a loop to keep accepting connections.
+    ///
+    /// The accepted socket is close-on-exec by default.
+    pub fn accept(&self) -> Result<Socket> {
+        let fd = accept4(self.fd, SockFlag::SOCK_CLOEXEC)?;
+        Ok(unsafe { Socket::from_raw_fd(fd) })
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        let _ = unistd::close(self.fd);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     extern crate shmemfdrs;
-    use super::Socket;
+    use super::{Socket, Listener};
     use std::os::unix::io::RawFd;
     #[cfg(feature = "zero_copy")]
     use zerocopy::AsBytes;
 
+    #[test]
+    fn test_listener_connect() {
+        let path = std::env::temp_dir().join("tiny-nix-ipc-test-listener.sock");
+        let listener = Listener::new(&path, true).unwrap();
+        let mut tx = Socket::connect(&path).unwrap();
+        let mut rx = listener.accept().unwrap();
+        let data = [0xDE, 0xAD, 0xBE, 0xEF];
+        let sent = tx.send_slice(&data[..], None).unwrap();
+        assert_eq!(sent, 4);
+        let mut rdata = [0; 4];
+        let (recvd, rfds) = rx.recv_into_slice::<[RawFd; 0]>(&mut rdata[..]).unwrap();
+        assert_eq!(recvd, 4);
+        assert_eq!(rfds, None);
+        assert_eq!(&rdata[..], &data[..]);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_recv_framed() {
+        let (mut rx, mut tx) = Socket::new_socketpair_stream().unwrap();
+        let data = [0xDE, 0xAD, 0xBE, 0xEF];
+        let sent = tx.send_slice_with_len(&data[..], None).unwrap();
+        assert_eq!(sent, 12);
+        let (rdata, rfds) = rx.recv_framed::<[RawFd; 0]>(64).unwrap();
+        assert_eq!(rfds, None);
+        assert_eq!(&rdata[..], &data[..]);
+    }
+
+    #[test]
+    fn test_recv_into_iovec_dyn() {
+        use nix::sys::uio::IoVec;
+        let (mut rx, mut tx) = Socket::new_socketpair().unwrap();
+        let data = [0xDE, 0xAD, 0xBE, 0xEF];
+        let sent = tx.send_slice(&data[..], None).unwrap();
+        assert_eq!(sent, 4);
+        let mut rdata = [0; 4];
+        let iov = [IoVec::from_mut_slice(&mut rdata[..])];
+        let (recvd, rfds) = rx.recv_into_iovec_dyn(&iov, 4).unwrap();
+        assert_eq!(recvd, 4);
+        assert!(rfds.is_empty());
+        assert_eq!(&rdata[..], &data[..]);
+    }
+
+    #[test]
+    fn test_send_all_stream() {
+        // 1 MiB is larger than the default AF_UNIX socket buffer, so the sender and
+        // receiver need to run concurrently: `send_all` would otherwise block forever
+        // once the kernel buffer fills, since nothing would be draining it.
+        let (mut rx, mut tx) = Socket::new_socketpair_stream().unwrap();
+        let data = vec![0x42u8; 1 << 20];
+        let expected = data.clone();
+        let receiver = std::thread::spawn(move || {
+            let mut received = vec![0u8; expected.len()];
+            let mut got = 0;
+            while got < received.len() {
+                let (n, _rfds) = rx.recv_into_slice::<[RawFd; 0]>(&mut received[got..]).unwrap();
+                got += n;
+            }
+            assert_eq!(received, expected);
+        });
+        let sent = tx.send_all(&data[..], None).unwrap();
+        assert_eq!(sent, data.len());
+        receiver.join().unwrap();
+    }
+
+    #[test]
+    fn test_recv_into_iovec_owned() {
+        use std::fs::File;
+        use std::io::{Read, Write, Seek, SeekFrom};
+        use std::os::unix::io::FromRawFd;
+        use std::ffi::CString;
+        use std::mem::ManuallyDrop;
+        use nix::sys::uio::IoVec;
+        let fd = shmemfdrs::create_shmem(CString::new("/test-owned").unwrap(), 6);
+        let mut orig_file = {
+            let mut file = unsafe { File::from_raw_fd(fd) };
+            file.write_all(b"hello\n").unwrap();
+            ManuallyDrop::new(file)
+        };
+        let (mut rx, mut tx) = Socket::new_socketpair().unwrap();
+        let data = [0xDE, 0xAD, 0xBE, 0xEF];
+        tx.send_slice(&data[..], Some(&[fd])).unwrap();
+        let mut rdata = [0; 4];
+        let iov = [IoVec::from_mut_slice(&mut rdata[..])];
+        let (recvd, mut rfds) = rx.recv_into_iovec_owned(&iov, 1).unwrap();
+        assert_eq!(recvd, 4);
+        assert_eq!(&rdata[..], &data[..]);
+        let owned_fd = rfds.pop().unwrap();
+        {
+            let mut file = File::from(owned_fd);
+            let mut content = String::new();
+            file.seek(SeekFrom::Start(0)).unwrap();
+            file.read_to_string(&mut content).unwrap();
+            assert_eq!(content, "hello\n");
+        }
+        unsafe { ManuallyDrop::drop(&mut orig_file); }
+    }
+
+    #[test]
+    fn test_ctrunc_detected() {
+        use std::fs::File;
+        use std::os::unix::io::FromRawFd;
+        use std::ffi::CString;
+        use std::mem::ManuallyDrop;
+        let fd = shmemfdrs::create_shmem(CString::new("/test-ctrunc").unwrap(), 6);
+        let _file = ManuallyDrop::new(unsafe { File::from_raw_fd(fd) });
+        let (mut rx, mut tx) = Socket::new_socketpair().unwrap();
+        let data = [0xDE, 0xAD, 0xBE, 0xEF];
+        tx.send_slice(&data[..], Some(&[fd])).unwrap();
+        let mut rdata = [0; 4];
+        // `F = [RawFd; 0]` sizes the control buffer for zero descriptors, so the one
+        // fd that was actually sent can't fit and gets reported as truncated.
+        let ret = rx.recv_into_slice::<[RawFd; 0]>(&mut rdata[..]);
+        assert!(ret.is_err());
+    }
+
     #[test]
     fn test_slice_success() {
         let (mut rx, mut tx) = Socket::new_socketpair().unwrap();